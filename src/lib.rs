@@ -1,7 +1,89 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fmt;
+use std::io::{self, Read, Write};
 use std::ops::Deref;
 
+fn double_sha256(bytes: &[u8]) -> [u8; 32] {
+    let first = Sha256::digest(bytes);
+    let second = Sha256::digest(first);
+    second.into()
+}
+
+/// Streaming counterpart to the `to_bytes`/`from_bytes` pattern used
+/// throughout this crate: writes directly to a `Write` instead of building a
+/// `Vec<u8>` up front.
+pub trait ConsensusEncode {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError>;
+}
+
+/// Streaming counterpart to `from_bytes`: reads directly from a `Read`
+/// instead of re-deriving slice offsets by hand.
+pub trait ConsensusDecode: Sized {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError>;
+}
+
+impl<T: ConsensusEncode> ConsensusEncode for Vec<T> {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        let mut written = CompactSize::new(self.len() as u64).consensus_encode(w)?;
+        for item in self {
+            written += item.consensus_encode(w)?;
+        }
+        Ok(written)
+    }
+}
+
+impl<T: ConsensusDecode> ConsensusDecode for Vec<T> {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let count = CompactSize::consensus_decode(r)?.value;
+        // `count` is attacker-controlled and read before a single element byte
+        // has been validated, so it must not be used to pre-allocate: a claimed
+        // count near `u64::MAX` would abort the process on the `with_capacity`
+        // call itself, long before `T::consensus_decode` ever got a chance to
+        // fail on truncated input.
+        let mut items = Vec::new();
+        for _ in 0..count {
+            items.push(T::consensus_decode(r)?);
+        }
+        Ok(items)
+    }
+}
+
+/// Reads exactly `len` bytes from `r` without trusting `len` enough to
+/// pre-allocate a buffer of that size: grows incrementally via `read_to_end`
+/// on a length-limited reader, so a bogus claimed length bounded only by the
+/// actual bytes available in `r` surfaces as `InsufficientBytes` instead of
+/// an allocation panic.
+fn read_exact_len<R: Read>(r: &mut R, len: usize) -> Result<Vec<u8>, BitcoinError> {
+    let mut buf = Vec::new();
+    r.take(len as u64)
+        .read_to_end(&mut buf)
+        .map_err(io_err_to_insufficient)?;
+    if buf.len() != len {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    Ok(buf)
+}
+
+fn encode_to_vec<T: ConsensusEncode>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value
+        .consensus_encode(&mut buf)
+        .expect("encoding into a Vec<u8> cannot fail");
+    buf
+}
+
+fn decode_from_slice<T: ConsensusDecode>(bytes: &[u8]) -> Result<(T, usize), BitcoinError> {
+    let mut cursor = bytes;
+    let value = T::consensus_decode(&mut cursor)?;
+    let consumed = bytes.len() - cursor.len();
+    Ok((value, consumed))
+}
+
+fn io_err_to_insufficient(_: io::Error) -> BitcoinError {
+    BitcoinError::InsufficientBytes
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct CompactSize {
     pub value: u64,
@@ -19,7 +101,17 @@ impl CompactSize {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        match self.value {
+        encode_to_vec(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        decode_from_slice(bytes)
+    }
+}
+
+impl ConsensusEncode for CompactSize {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        let bytes: Vec<u8> = match self.value {
             0..=0xFC => vec![self.value as u8],
             0xFD..=0xFFFF => {
                 let mut bytes = vec![0xFD];
@@ -36,46 +128,127 @@ impl CompactSize {
                 bytes.extend(&self.value.to_le_bytes());
                 bytes
             }
-        }
+        };
+        w.write_all(&bytes).map_err(io_err_to_insufficient)?;
+        Ok(bytes.len())
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.is_empty() {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-
-        match bytes[0] {
-            n @ 0x00..=0xFC => Ok((Self::new(n as u64), 1)),
+impl ConsensusDecode for CompactSize {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let mut prefix = [0u8; 1];
+        r.read_exact(&mut prefix).map_err(io_err_to_insufficient)?;
+        match prefix[0] {
+            n @ 0x00..=0xFC => Ok(Self::new(n as u64)),
             0xFD => {
-                if bytes.len() < 3 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let val = u16::from_le_bytes([bytes[1], bytes[2]]) as u64;
-                Ok((Self::new(val), 3))
+                let mut buf = [0u8; 2];
+                r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+                Ok(Self::new(u16::from_le_bytes(buf) as u64))
             }
             0xFE => {
-                if bytes.len() < 5 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let val = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as u64;
-                Ok((Self::new(val), 5))
+                let mut buf = [0u8; 4];
+                r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+                Ok(Self::new(u32::from_le_bytes(buf) as u64))
             }
             0xFF => {
-                if bytes.len() < 9 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let val = u64::from_le_bytes([
-                    bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8],
-                ]);
-                Ok((Self::new(val), 9))
+                let mut buf = [0u8; 8];
+                r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+                Ok(Self::new(u64::from_le_bytes(buf)))
+            }
+        }
+    }
+}
+
+/// A denser alternative to [`CompactSize`] for length-prefixing values inside
+/// custom, non-Bitcoin-P2P framing: a little-endian base-128 varint (LEB128
+/// style) instead of a single-byte-prefix-plus-width encoding.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ShortVecLen {
+    pub value: u64,
+}
+
+impl ShortVecLen {
+    pub fn new(value: u64) -> Self {
+        Self { value }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut remaining = self.value;
+        loop {
+            let mut byte = (remaining & 0x7f) as u8;
+            remaining >>= 7;
+            if remaining != 0 {
+                byte |= 0x80;
+            }
+            bytes.push(byte);
+            if remaining == 0 {
+                break;
+            }
+        }
+        bytes
+    }
+
+    /// Decodes a varint, returning the value and the number of bytes consumed.
+    /// Errors with `InsufficientBytes` if the stream ends mid-varint, or
+    /// `InvalidFormat` if the first 10 bytes all carry the continuation bit (an
+    /// encoding longer than the 10 bytes any `u64` requires).
+    pub fn from_bytes(bytes: &[u8]) -> Result<(u64, usize), BitcoinError> {
+        let mut value: u64 = 0;
+        let mut shift = 0u32;
+        for i in 0..10 {
+            if i >= bytes.len() {
+                return Err(BitcoinError::InsufficientBytes);
+            }
+            let byte = bytes[i];
+            value |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok((value, i + 1));
             }
         }
+        Err(BitcoinError::InvalidFormat)
     }
 }
 
+/// A transaction's double-SHA256 digest, stored in internal (little-endian,
+/// first-byte-first) order. Both `Serialize`/`Deserialize` and `Display`
+/// hex-encode the bytes in this internal order; use [`Txid::to_rpc_hex`] for
+/// the byte-reversed form Bitcoin Core and block explorers display.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Txid(pub [u8; 32]);
 
+impl Txid {
+    /// Hex-encodes the txid the way Bitcoin RPCs and explorers display it:
+    /// byte-reversed relative to the internal, wire-order representation.
+    pub fn to_rpc_hex(&self) -> String {
+        let mut reversed = self.0;
+        reversed.reverse();
+        hex::encode(reversed)
+    }
+}
+
+impl ConsensusEncode for Txid {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        w.write_all(&self.0).map_err(io_err_to_insufficient)?;
+        Ok(32)
+    }
+}
+
+impl ConsensusDecode for Txid {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let mut buf = [0u8; 32];
+        r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+        Ok(Txid(buf))
+    }
+}
+
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
 impl Serialize for Txid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -116,19 +289,33 @@ impl OutPoint {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = self.txid.0.to_vec();
-        bytes.extend(&self.vout.to_le_bytes());
-        bytes
+        encode_to_vec(self)
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 36 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let mut txid = [0u8; 32];
-        txid.copy_from_slice(&bytes[0..32]);
-        let vout = u32::from_le_bytes([bytes[32], bytes[33], bytes[34], bytes[35]]);
-        Ok((Self::new(txid, vout), 36))
+        decode_from_slice(bytes)
+    }
+}
+
+impl ConsensusEncode for OutPoint {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        let mut written = self.txid.consensus_encode(w)?;
+        w.write_all(&self.vout.to_le_bytes())
+            .map_err(io_err_to_insufficient)?;
+        written += 4;
+        Ok(written)
+    }
+}
+
+impl ConsensusDecode for OutPoint {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let txid = Txid::consensus_decode(r)?;
+        let mut buf = [0u8; 4];
+        r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+        Ok(Self {
+            txid,
+            vout: u32::from_le_bytes(buf),
+        })
     }
 }
 
@@ -143,20 +330,96 @@ impl Script {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = CompactSize::new(self.bytes.len() as u64).to_bytes();
-        result.extend(&self.bytes);
-        result
+        encode_to_vec(self)
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (len_prefix, consumed) = CompactSize::from_bytes(bytes)?;
-        let len = len_prefix.value as usize;
-        let end = consumed + len;
-        if bytes.len() < end {
-            return Err(BitcoinError::InsufficientBytes);
+        decode_from_slice(bytes)
+    }
+
+    /// Disassembles the script into a sequence of data pushes and opcodes.
+    pub fn instructions(&self) -> Result<Vec<Instruction>, BitcoinError> {
+        let bytes = &self.bytes;
+        let mut instructions = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let opcode_byte = bytes[offset];
+            offset += 1;
+            let push_len = match opcode_byte {
+                0x01..=0x4b => Some(opcode_byte as usize),
+                0x4c => {
+                    if offset >= bytes.len() {
+                        return Err(BitcoinError::InvalidFormat);
+                    }
+                    let len = bytes[offset] as usize;
+                    offset += 1;
+                    Some(len)
+                }
+                0x4d => {
+                    if offset + 2 > bytes.len() {
+                        return Err(BitcoinError::InvalidFormat);
+                    }
+                    let len = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) as usize;
+                    offset += 2;
+                    Some(len)
+                }
+                0x4e => {
+                    if offset + 4 > bytes.len() {
+                        return Err(BitcoinError::InvalidFormat);
+                    }
+                    let len = u32::from_le_bytes([
+                        bytes[offset],
+                        bytes[offset + 1],
+                        bytes[offset + 2],
+                        bytes[offset + 3],
+                    ]) as usize;
+                    offset += 4;
+                    Some(len)
+                }
+                _ => None,
+            };
+
+            match push_len {
+                Some(len) => {
+                    if offset + len > bytes.len() {
+                        return Err(BitcoinError::InvalidFormat);
+                    }
+                    instructions.push(Instruction::PushBytes(bytes[offset..offset + len].to_vec()));
+                    offset += len;
+                }
+                None => instructions.push(Instruction::Op(Opcode::from_byte(opcode_byte))),
+            }
         }
-        let script_bytes = bytes[consumed..end].to_vec();
-        Ok((Self::new(script_bytes), end))
+        Ok(instructions)
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instructions() {
+            Ok(instructions) => {
+                let rendered: Vec<String> = instructions.iter().map(|i| i.to_string()).collect();
+                write!(f, "{}", rendered.join(" "))
+            }
+            Err(_) => write!(f, "<invalid script: {}>", hex::encode(&self.bytes)),
+        }
+    }
+}
+
+impl ConsensusEncode for Script {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        let mut written = CompactSize::new(self.bytes.len() as u64).consensus_encode(w)?;
+        w.write_all(&self.bytes).map_err(io_err_to_insufficient)?;
+        written += self.bytes.len();
+        Ok(written)
+    }
+}
+
+impl ConsensusDecode for Script {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let len = CompactSize::consensus_decode(r)?.value as usize;
+        let buf = read_exact_len(r, len)?;
+        Ok(Self::new(buf))
     }
 }
 
@@ -167,11 +430,123 @@ impl Deref for Script {
     }
 }
 
+/// A single parsed script element: either a data push or a non-push opcode.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Instruction {
+    PushBytes(Vec<u8>),
+    Op(Opcode),
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::PushBytes(data) => write!(f, "<{}>", hex::encode(data)),
+            Instruction::Op(opcode) => write!(f, "{}", opcode),
+        }
+    }
+}
+
+/// A non-push script opcode. Only the subset this crate can disassemble is
+/// named; anything else round-trips through `OP_UNKNOWN`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub enum Opcode {
+    OP_0,
+    OP_1,
+    OP_2,
+    OP_3,
+    OP_4,
+    OP_5,
+    OP_6,
+    OP_7,
+    OP_8,
+    OP_9,
+    OP_10,
+    OP_11,
+    OP_12,
+    OP_13,
+    OP_14,
+    OP_15,
+    OP_16,
+    OP_DUP,
+    OP_HASH160,
+    OP_EQUAL,
+    OP_EQUALVERIFY,
+    OP_CHECKSIG,
+    OP_RETURN,
+    OP_UNKNOWN(u8),
+}
+
+impl Opcode {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0x00 => Opcode::OP_0,
+            0x51 => Opcode::OP_1,
+            0x52 => Opcode::OP_2,
+            0x53 => Opcode::OP_3,
+            0x54 => Opcode::OP_4,
+            0x55 => Opcode::OP_5,
+            0x56 => Opcode::OP_6,
+            0x57 => Opcode::OP_7,
+            0x58 => Opcode::OP_8,
+            0x59 => Opcode::OP_9,
+            0x5a => Opcode::OP_10,
+            0x5b => Opcode::OP_11,
+            0x5c => Opcode::OP_12,
+            0x5d => Opcode::OP_13,
+            0x5e => Opcode::OP_14,
+            0x5f => Opcode::OP_15,
+            0x60 => Opcode::OP_16,
+            0x6a => Opcode::OP_RETURN,
+            0x76 => Opcode::OP_DUP,
+            0x87 => Opcode::OP_EQUAL,
+            0x88 => Opcode::OP_EQUALVERIFY,
+            0xa9 => Opcode::OP_HASH160,
+            0xac => Opcode::OP_CHECKSIG,
+            other => Opcode::OP_UNKNOWN(other),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::OP_0 => write!(f, "OP_0"),
+            Opcode::OP_1 => write!(f, "OP_1"),
+            Opcode::OP_2 => write!(f, "OP_2"),
+            Opcode::OP_3 => write!(f, "OP_3"),
+            Opcode::OP_4 => write!(f, "OP_4"),
+            Opcode::OP_5 => write!(f, "OP_5"),
+            Opcode::OP_6 => write!(f, "OP_6"),
+            Opcode::OP_7 => write!(f, "OP_7"),
+            Opcode::OP_8 => write!(f, "OP_8"),
+            Opcode::OP_9 => write!(f, "OP_9"),
+            Opcode::OP_10 => write!(f, "OP_10"),
+            Opcode::OP_11 => write!(f, "OP_11"),
+            Opcode::OP_12 => write!(f, "OP_12"),
+            Opcode::OP_13 => write!(f, "OP_13"),
+            Opcode::OP_14 => write!(f, "OP_14"),
+            Opcode::OP_15 => write!(f, "OP_15"),
+            Opcode::OP_16 => write!(f, "OP_16"),
+            Opcode::OP_DUP => write!(f, "OP_DUP"),
+            Opcode::OP_HASH160 => write!(f, "OP_HASH160"),
+            Opcode::OP_EQUAL => write!(f, "OP_EQUAL"),
+            Opcode::OP_EQUALVERIFY => write!(f, "OP_EQUALVERIFY"),
+            Opcode::OP_CHECKSIG => write!(f, "OP_CHECKSIG"),
+            Opcode::OP_RETURN => write!(f, "OP_RETURN"),
+            Opcode::OP_UNKNOWN(byte) => write!(f, "OP_UNKNOWN(0x{:02x})", byte),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct TransactionInput {
     pub previous_output: OutPoint,
     pub script_sig: Script,
     pub sequence: u32,
+    /// Witness stack (BIP-141). Empty for a legacy input; never written to the
+    /// legacy wire format, only to the SegWit witness section.
+    pub witness: Vec<Vec<u8>>,
 }
 
 impl TransactionInput {
@@ -180,30 +555,114 @@ impl TransactionInput {
             previous_output,
             script_sig,
             sequence,
+            witness: Vec::new(),
+        }
+    }
+
+    pub fn with_witness(
+        previous_output: OutPoint,
+        script_sig: Script,
+        sequence: u32,
+        witness: Vec<Vec<u8>>,
+    ) -> Self {
+        Self {
+            previous_output,
+            script_sig,
+            sequence,
+            witness,
         }
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = self.previous_output.to_bytes();
-        result.extend(self.script_sig.to_bytes());
-        result.extend(&self.sequence.to_le_bytes());
-        result
+        encode_to_vec(self)
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (outpoint, consumed1) = OutPoint::from_bytes(bytes)?;
-        let (script, consumed2) = Script::from_bytes(&bytes[consumed1..])?;
-        if bytes.len() < consumed1 + consumed2 + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+        decode_from_slice(bytes)
+    }
+
+    fn witness_to_bytes(&self) -> Vec<u8> {
+        let mut result = CompactSize::new(self.witness.len() as u64).to_bytes();
+        for item in &self.witness {
+            result.extend(CompactSize::new(item.len() as u64).to_bytes());
+            result.extend(item);
+        }
+        result
+    }
+
+    /// Reads one input's witness stack from a SegWit witness section.
+    fn witness_consensus_decode<R: Read>(r: &mut R) -> Result<Vec<Vec<u8>>, BitcoinError> {
+        let item_count = CompactSize::consensus_decode(r)?.value;
+        let mut witness = Vec::new();
+        for _ in 0..item_count {
+            let len = CompactSize::consensus_decode(r)?.value as usize;
+            witness.push(read_exact_len(r, len)?);
         }
-        let sequence = u32::from_le_bytes([
-            bytes[consumed1 + consumed2],
-            bytes[consumed1 + consumed2 + 1],
-            bytes[consumed1 + consumed2 + 2],
-            bytes[consumed1 + consumed2 + 3],
-        ]);
-        let total = consumed1 + consumed2 + 4;
-        Ok((Self::new(outpoint, script, sequence), total))
+        Ok(witness)
+    }
+}
+
+impl ConsensusEncode for TransactionInput {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        let mut written = self.previous_output.consensus_encode(w)?;
+        written += self.script_sig.consensus_encode(w)?;
+        w.write_all(&self.sequence.to_le_bytes())
+            .map_err(io_err_to_insufficient)?;
+        written += 4;
+        Ok(written)
+    }
+}
+
+impl ConsensusDecode for TransactionInput {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let previous_output = OutPoint::consensus_decode(r)?;
+        let script_sig = Script::consensus_decode(r)?;
+        let mut buf = [0u8; 4];
+        r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+        Ok(Self::new(previous_output, script_sig, u32::from_le_bytes(buf)))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TxOut {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TxOut {
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        decode_from_slice(bytes)
+    }
+}
+
+impl ConsensusEncode for TxOut {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        w.write_all(&self.value.to_le_bytes())
+            .map_err(io_err_to_insufficient)?;
+        let mut written = 8;
+        written += self.script_pubkey.consensus_encode(w)?;
+        Ok(written)
+    }
+}
+
+impl ConsensusDecode for TxOut {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let mut buf = [0u8; 8];
+        r.read_exact(&mut buf).map_err(io_err_to_insufficient)?;
+        let value = u64::from_le_bytes(buf);
+        let script_pubkey = Script::consensus_decode(r)?;
+        Ok(Self::new(value, script_pubkey))
     }
 }
 
@@ -211,51 +670,133 @@ impl TransactionInput {
 pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TxOut>,
     pub lock_time: u32,
 }
 
 impl BitcoinTransaction {
-    pub fn new(version: u32, inputs: Vec<TransactionInput>, lock_time: u32) -> Self {
+    pub fn new(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TxOut>,
+        lock_time: u32,
+    ) -> Self {
         Self {
             version,
             inputs,
+            outputs,
             lock_time,
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
+    fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// Legacy (pre-BIP-141) serialization, with no marker/flag/witness section.
+    /// This is the form hashed by [`BitcoinTransaction::txid`].
+    pub fn to_bytes_legacy(&self) -> Vec<u8> {
         let mut result = self.version.to_le_bytes().to_vec();
         result.extend(CompactSize::new(self.inputs.len() as u64).to_bytes());
         for input in &self.inputs {
             result.extend(input.to_bytes());
         }
+        result.extend(CompactSize::new(self.outputs.len() as u64).to_bytes());
+        for output in &self.outputs {
+            result.extend(output.to_bytes());
+        }
         result.extend(self.lock_time.to_le_bytes());
         result
     }
 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(self)
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 4 {
-            return Err(BitcoinError::InsufficientBytes);
+        decode_from_slice(bytes)
+    }
+
+    /// The transaction's identifying hash: double-SHA256 of the legacy
+    /// (non-witness) serialization. Unaffected by witness data, per BIP-141.
+    pub fn txid(&self) -> Txid {
+        Txid(double_sha256(&self.to_bytes_legacy()))
+    }
+
+    /// Double-SHA256 of the full witness serialization. Identical to
+    /// [`BitcoinTransaction::txid`] when the transaction carries no witness data.
+    pub fn wtxid(&self) -> Txid {
+        Txid(double_sha256(&self.to_bytes()))
+    }
+}
+
+impl ConsensusEncode for BitcoinTransaction {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, BitcoinError> {
+        let mut written = 4;
+        w.write_all(&self.version.to_le_bytes())
+            .map_err(io_err_to_insufficient)?;
+
+        let segwit = self.has_witness();
+        if segwit {
+            w.write_all(&[0x00, 0x01]).map_err(io_err_to_insufficient)?;
+            written += 2;
         }
-        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        let (input_count, consumed1) = CompactSize::from_bytes(&bytes[4..])?;
-        let mut inputs = vec![];
-        let mut offset = 4 + consumed1;
-        for _ in 0..input_count.value {
-            let (input, consumed) = TransactionInput::from_bytes(&bytes[offset..])?;
-            inputs.push(input);
-            offset += consumed;
+
+        written += self.inputs.consensus_encode(w)?;
+        written += self.outputs.consensus_encode(w)?;
+
+        if segwit {
+            for input in &self.inputs {
+                let witness_bytes = input.witness_to_bytes();
+                w.write_all(&witness_bytes).map_err(io_err_to_insufficient)?;
+                written += witness_bytes.len();
+            }
         }
-        if bytes.len() < offset + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+
+        w.write_all(&self.lock_time.to_le_bytes())
+            .map_err(io_err_to_insufficient)?;
+        written += 4;
+        Ok(written)
+    }
+}
+
+impl ConsensusDecode for BitcoinTransaction {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let mut version_buf = [0u8; 4];
+        r.read_exact(&mut version_buf)
+            .map_err(io_err_to_insufficient)?;
+        let version = u32::from_le_bytes(version_buf);
+
+        let mut marker = [0u8; 1];
+        r.read_exact(&mut marker).map_err(io_err_to_insufficient)?;
+
+        let is_segwit = marker[0] == 0x00;
+        let mut body: Box<dyn Read + '_> = if is_segwit {
+            let mut flag = [0u8; 1];
+            r.read_exact(&mut flag).map_err(io_err_to_insufficient)?;
+            if flag[0] != 0x01 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            Box::new(r)
+        } else {
+            Box::new(io::Cursor::new(marker).chain(r))
+        };
+
+        let mut inputs: Vec<TransactionInput> = Vec::consensus_decode(&mut body)?;
+        let outputs: Vec<TxOut> = Vec::consensus_decode(&mut body)?;
+
+        if is_segwit {
+            for input in &mut inputs {
+                input.witness = TransactionInput::witness_consensus_decode(&mut body)?;
+            }
         }
-        let lock_time = u32::from_le_bytes([
-            bytes[offset],
-            bytes[offset + 1],
-            bytes[offset + 2],
-            bytes[offset + 3],
-        ]);
-        Ok((Self::new(version, inputs, lock_time), offset + 4))
+
+        let mut lock_time_buf = [0u8; 4];
+        body.read_exact(&mut lock_time_buf)
+            .map_err(io_err_to_insufficient)?;
+        let lock_time = u32::from_le_bytes(lock_time_buf);
+
+        Ok(Self::new(version, inputs, outputs, lock_time))
     }
 }
 
@@ -278,7 +819,346 @@ impl fmt::Display for BitcoinTransaction {
                 hex::encode(&*input.script_sig)
             )?;
             writeln!(f, "  Sequence: {}", input.sequence)?;
+            if !input.witness.is_empty() {
+                writeln!(f, "  Witness items: {}", input.witness.len())?;
+            }
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            writeln!(f, "Output {}:", i)?;
+            writeln!(f, "  Value: {}", output.value)?;
+            writeln!(
+                f,
+                "  Script Pubkey ({} bytes): {}",
+                output.script_pubkey.len(),
+                hex::encode(&*output.script_pubkey)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub fn new(
+        version: u32,
+        prev_blockhash: [u8; 32],
+        merkle_root: [u8; 32],
+        time: u32,
+        bits: u32,
+        nonce: u32,
+    ) -> Self {
+        Self {
+            version,
+            prev_blockhash,
+            merkle_root,
+            time,
+            bits,
+            nonce,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(80);
+        result.extend(self.version.to_le_bytes());
+        result.extend(self.prev_blockhash);
+        result.extend(self.merkle_root);
+        result.extend(self.time.to_le_bytes());
+        result.extend(self.bits.to_le_bytes());
+        result.extend(self.nonce.to_le_bytes());
+        result
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < 80 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let mut prev_blockhash = [0u8; 32];
+        prev_blockhash.copy_from_slice(&bytes[4..36]);
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[36..68]);
+        let time = u32::from_le_bytes([bytes[68], bytes[69], bytes[70], bytes[71]]);
+        let bits = u32::from_le_bytes([bytes[72], bytes[73], bytes[74], bytes[75]]);
+        let nonce = u32::from_le_bytes([bytes[76], bytes[77], bytes[78], bytes[79]]);
+        Ok((
+            Self::new(version, prev_blockhash, merkle_root, time, bits, nonce),
+            80,
+        ))
+    }
+
+    /// Decompresses the `bits` field into the 256-bit target, as a
+    /// little-endian byte array. A mantissa with its sign bit (`0x0080_0000`)
+    /// set is treated as negative and clamped to zero, matching Bitcoin Core.
+    pub fn target(&self) -> [u8; 32] {
+        let exponent = (self.bits >> 24) as i32;
+        let mantissa = if self.bits & 0x0080_0000 != 0 {
+            0
+        } else {
+            self.bits & 0x007f_ffff
+        };
+
+        let mut target = [0u8; 32];
+        if mantissa == 0 {
+            return target;
+        }
+        let mantissa_bytes = mantissa.to_le_bytes();
+        let shift = exponent - 3;
+        for i in 0..3i32 {
+            let pos = shift + i;
+            if pos >= 0 && (pos as usize) < 32 {
+                target[pos as usize] = mantissa_bytes[i as usize];
+            }
+        }
+        target
+    }
+
+    /// Double-SHA256s the 80-byte header and checks the result, read as a
+    /// little-endian 256-bit integer, does not exceed [`BlockHeader::target`].
+    pub fn validate_pow(&self) -> Result<(), BitcoinError> {
+        let hash = double_sha256(&self.to_bytes());
+        let target = self.target();
+        for i in (0..32).rev() {
+            if hash[i] != target[i] {
+                return if hash[i] > target[i] {
+                    Err(BitcoinError::InvalidFormat)
+                } else {
+                    Ok(())
+                };
+            }
         }
         Ok(())
     }
 }
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub txdata: Vec<BitcoinTransaction>,
+}
+
+impl Block {
+    pub fn new(header: BlockHeader, txdata: Vec<BitcoinTransaction>) -> Self {
+        Self { header, txdata }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = self.header.to_bytes();
+        result.extend(CompactSize::new(self.txdata.len() as u64).to_bytes());
+        for tx in &self.txdata {
+            result.extend(tx.to_bytes());
+        }
+        result
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (header, consumed1) = BlockHeader::from_bytes(bytes)?;
+        let (tx_count, consumed2) = CompactSize::from_bytes(&bytes[consumed1..])?;
+        let mut offset = consumed1 + consumed2;
+        let mut txdata = vec![];
+        for _ in 0..tx_count.value {
+            let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes[offset..])?;
+            txdata.push(tx);
+            offset += consumed;
+        }
+        Ok((Self::new(header, txdata), offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_size_round_trips_each_width_boundary() {
+        for value in [0u64, 0xFC, 0xFD, 0xFFFF, 0x1_0000, 0xFFFF_FFFF, 0x1_0000_0000, u64::MAX] {
+            let encoded = CompactSize::new(value).to_bytes();
+            let (decoded, consumed) = CompactSize::from_bytes(&encoded).unwrap();
+            assert_eq!(decoded.value, value);
+            assert_eq!(consumed, encoded.len());
+        }
+    }
+
+    #[test]
+    fn compact_size_from_bytes_rejects_truncated_input() {
+        assert_eq!(
+            CompactSize::from_bytes(&[0xFF, 0x01, 0x02]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            CompactSize::from_bytes(&[]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn short_vec_len_round_trips_boundary_values() {
+        for value in [0u64, 1, 127, 128, 16384, u32::MAX as u64, u64::MAX] {
+            let encoded = ShortVecLen::new(value).to_bytes();
+            let (decoded, consumed) = ShortVecLen::from_bytes(&encoded).unwrap();
+            assert_eq!(decoded, value);
+            assert_eq!(consumed, encoded.len());
+        }
+    }
+
+    #[test]
+    fn short_vec_len_from_bytes_rejects_truncated_input() {
+        // 0x80 has its continuation bit set but no following byte.
+        assert_eq!(
+            ShortVecLen::from_bytes(&[0x80]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn short_vec_len_from_bytes_rejects_overlong_encoding() {
+        // 11 continuation bytes: wider than any u64 needs.
+        let overlong = [0x80u8; 11];
+        assert_eq!(
+            ShortVecLen::from_bytes(&overlong),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn transaction_round_trips_legacy_wire_format() {
+        let input = TransactionInput::new(
+            OutPoint::new([0x11; 32], 0),
+            Script::new(vec![0x76, 0xa9]),
+            0xFFFF_FFFF,
+        );
+        let output = TxOut::new(5_000_000_000, Script::new(vec![0x88, 0xac]));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![output], 0);
+
+        let encoded = tx.to_bytes();
+        let (decoded, consumed) = BitcoinTransaction::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(decoded.txid(), decoded.wtxid());
+    }
+
+    #[test]
+    fn transaction_round_trips_segwit_wire_format_with_marker_and_flag() {
+        let input = TransactionInput::with_witness(
+            OutPoint::new([0x22; 32], 1),
+            Script::new(vec![]),
+            0,
+            vec![vec![0xde, 0xad], vec![0xbe, 0xef]],
+        );
+        let output = TxOut::new(1, Script::new(vec![0x6a]));
+        let tx = BitcoinTransaction::new(2, vec![input], vec![output], 500_000);
+
+        let encoded = tx.to_bytes();
+        assert_eq!(&encoded[4..6], &[0x00, 0x01]);
+
+        let (decoded, consumed) = BitcoinTransaction::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(consumed, encoded.len());
+        assert_ne!(decoded.txid(), decoded.wtxid());
+    }
+
+    #[test]
+    fn script_consensus_decode_rejects_huge_claimed_length_without_panicking() {
+        // CompactSize prefix 0xFF + u64::MAX: claims an absurd script length
+        // with no data behind it. Must error, not abort via allocation panic.
+        let mut malicious = vec![0xFFu8];
+        malicious.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            Script::from_bytes(&malicious),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn script_disassembles_standard_p2pkh_pattern() {
+        let mut bytes = Vec::new();
+        bytes.push(0x76); // OP_DUP
+        bytes.push(0xa9); // OP_HASH160
+        bytes.push(0x14); // push 20 bytes
+        bytes.extend_from_slice(&[0xAB; 20]);
+        bytes.push(0x88); // OP_EQUALVERIFY
+        bytes.push(0xac); // OP_CHECKSIG
+
+        let script = Script::new(bytes);
+        let instructions = script.instructions().unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::Op(Opcode::OP_DUP),
+                Instruction::Op(Opcode::OP_HASH160),
+                Instruction::PushBytes(vec![0xAB; 20]),
+                Instruction::Op(Opcode::OP_EQUALVERIFY),
+                Instruction::Op(Opcode::OP_CHECKSIG),
+            ]
+        );
+        assert_eq!(
+            script.to_string(),
+            format!("OP_DUP OP_HASH160 <{}> OP_EQUALVERIFY OP_CHECKSIG", hex::encode([0xAB; 20]))
+        );
+    }
+
+    #[test]
+    fn script_instructions_errors_on_truncated_push() {
+        // OP_PUSHDATA1 claims 10 bytes but only 2 follow.
+        let script = Script::new(vec![0x4c, 0x0a, 0x01, 0x02]);
+        assert_eq!(script.instructions(), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn block_header_round_trips_80_bytes() {
+        let header = BlockHeader::new(1, [0x01; 32], [0x02; 32], 1_700_000_000, 0x1d00ffff, 0);
+        let encoded = header.to_bytes();
+        assert_eq!(encoded.len(), 80);
+        let (decoded, consumed) = BlockHeader::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(consumed, 80);
+    }
+
+    #[test]
+    fn block_header_validate_pow_agrees_with_a_manual_hash_vs_target_comparison() {
+        let header = BlockHeader::new(1, [0x01; 32], [0x02; 32], 1_700_000_000, 0x1d00ffff, 0);
+        let hash = double_sha256(&header.to_bytes());
+        let target = header.target();
+        let hash_exceeds_target = (0..32).rev().find_map(|i| {
+            if hash[i] != target[i] {
+                Some(hash[i] > target[i])
+            } else {
+                None
+            }
+        }).unwrap_or(false);
+
+        match header.validate_pow() {
+            Ok(()) => assert!(!hash_exceeds_target),
+            Err(BitcoinError::InvalidFormat) => assert!(hash_exceeds_target),
+            Err(other) => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn block_header_target_decompresses_known_genesis_bits() {
+        // Bitcoin mainnet genesis `bits` (0x1d00ffff) decompresses to the
+        // well-known max-difficulty-1 target.
+        let header = BlockHeader::new(1, [0; 32], [0; 32], 0, 0x1d00ffff, 0);
+        let target = header.target();
+        // exponent=0x1d (29), mantissa=0x00ffff -> mantissa << (8 * (29 - 3))
+        // places the mantissa's bytes at little-endian indices 26 and 27.
+        let mut expected = [0u8; 32];
+        expected[26] = 0xff;
+        expected[27] = 0xff;
+        assert_eq!(target, expected);
+    }
+
+    #[test]
+    fn block_header_target_clamps_negative_mantissa_to_zero() {
+        let header = BlockHeader::new(1, [0; 32], [0; 32], 0, 0x0180_0000, 0);
+        assert_eq!(header.target(), [0u8; 32]);
+    }
+}